@@ -0,0 +1,100 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorInternalServerError, ErrorUnauthorized},
+    web, Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::Database;
+
+/// The token-scoped identity attached to a request by [`RequireAuth`].
+/// Handlers pull this out with `web::ReqData<Principal>` and consult
+/// [`Principal::allows`] before touching a collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Principal {
+    pub token: String,
+    pub allowed_models: Vec<String>,
+}
+
+impl Principal {
+    /// `"*"` in `allowed_models` grants access to every collection.
+    pub fn allows(&self, model_type: &str) -> bool {
+        self.allowed_models.iter().any(|m| m == "*" || m == model_type)
+    }
+
+    /// Whether this principal holds the global `"*"` capability, required
+    /// for actions that aren't scoped to a single collection (e.g. log
+    /// compaction).
+    pub fn is_admin(&self) -> bool {
+        self.allowed_models.iter().any(|m| m == "*")
+    }
+}
+
+/// Middleware equivalent of the `AsyncRequireAuthorizationLayer` pattern:
+/// rejects requests with a missing/unknown bearer token with 401, and
+/// otherwise attaches the resolved [`Principal`] to the request so handlers
+/// can enforce per-collection authorization.
+pub struct RequireAuth;
+
+impl<S, B> Transform<S, ServiceRequest> for RequireAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireAuthMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequireAuthMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let db = req.app_data::<web::Data<Database>>().cloned();
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.to_string());
+
+        Box::pin(async move {
+            let token = token.ok_or_else(|| ErrorUnauthorized("missing bearer token"))?;
+            let db = db.ok_or_else(|| ErrorInternalServerError("database not configured"))?;
+
+            let allowed_models = db
+                .lookup_token(&token)
+                .map_err(|e| ErrorInternalServerError(e.to_string()))?
+                .ok_or_else(|| ErrorUnauthorized("invalid bearer token"))?;
+
+            req.extensions_mut().insert(Principal { token, allowed_models });
+
+            service.call(req).await
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use std::fmt;
+
+use crate::ValidationFailure;
+
+/// The crate-wide error type. Every fallible `Database` method and request
+/// handler returns `Result<_, AppError>`, so their failures turn into a
+/// well-formed JSON error response instead of a panic or an ad-hoc
+/// `.body(e.to_string())`. The SSE stream handler only logs a failed
+/// catch-up, and the auth middleware rejects with actix's own plain-text
+/// 401 errors.
+#[derive(Debug)]
+pub enum AppError {
+    /// A batch of transactions failed JSON-Schema validation.
+    Validation(Vec<ValidationFailure>),
+    /// A transaction's `action` was something other than
+    /// `create`/`update`/`delete`.
+    InvalidAction(String),
+    /// A referenced resource (e.g. an interactive tx session) doesn't
+    /// exist, was already consumed, or was swept for being idle too long.
+    NotFound(String),
+    /// Stored or incoming data didn't round-trip through serde_json.
+    Serialization(serde_json::Error),
+    /// The underlying SQLite connection returned an error.
+    Database(rusqlite::Error),
+    /// A `Mutex` guarding shared state was poisoned by a panicking holder.
+    LockPoisoned,
+    /// A collection's stored JSON-Schema document (`model_type`) failed to
+    /// compile, so whether incoming writes are valid can't be determined.
+    InvalidSchema(String, String),
+    /// A caller tried to register a JSON-Schema document that doesn't
+    /// itself compile.
+    SchemaRejected(String, String),
+    /// A transaction's `data` wasn't shaped the way its `action` requires
+    /// (e.g. a non-object `data` on a `create`/`update`).
+    InvalidData(String),
+    /// The caller isn't the principal that owns the resource it tried to
+    /// act on (e.g. another token's interactive tx session).
+    Forbidden(String),
+}
+
+impl AppError {
+    fn kind(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_failed",
+            AppError::InvalidAction(_) => "invalid_action",
+            AppError::NotFound(_) => "not_found",
+            AppError::Serialization(_) => "serialization_error",
+            AppError::Database(_) => "database_error",
+            AppError::LockPoisoned => "lock_poisoned",
+            AppError::InvalidSchema(_, _) => "invalid_schema",
+            AppError::SchemaRejected(_, _) => "schema_rejected",
+            AppError::InvalidData(_) => "invalid_data",
+            AppError::Forbidden(_) => "forbidden",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Validation(_) => write!(f, "one or more transactions failed schema validation"),
+            AppError::InvalidAction(action) => write!(f, "invalid action: {}", action),
+            AppError::NotFound(detail) => write!(f, "{}", detail),
+            AppError::Serialization(e) => write!(f, "serialization error: {}", e),
+            AppError::Database(e) => write!(f, "database error: {}", e),
+            AppError::LockPoisoned => write!(f, "an internal lock was poisoned"),
+            AppError::InvalidSchema(model_type, detail) => write!(
+                f, "schema registered for '{}' does not compile: {}", model_type, detail
+            ),
+            AppError::SchemaRejected(model_type, detail) => write!(
+                f, "schema for '{}' does not compile: {}", model_type, detail
+            ),
+            AppError::InvalidData(detail) => write!(f, "{}", detail),
+            AppError::Forbidden(detail) => write!(f, "{}", detail),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Database(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Serialization(e)
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for AppError {
+    fn from(_: std::sync::PoisonError<T>) -> Self {
+        AppError::LockPoisoned
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidAction(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Serialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::LockPoisoned => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::InvalidSchema(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::SchemaRejected(_, _) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidData(_) => StatusCode::BAD_REQUEST,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let AppError::Validation(failures) = self {
+            return HttpResponse::build(self.status_code()).json(serde_json::json!({
+                "error": self.kind(),
+                "failures": failures,
+            }));
+        }
+
+        HttpResponse::build(self.status_code()).json(serde_json::json!({
+            "error": self.kind(),
+            "detail": self.to_string(),
+        }))
+    }
+}
@@ -1,12 +1,75 @@
 use actix_web::{web, App, HttpResponse, HttpServer, Responder};
 use actix_files::Files;
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, Result, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
 use chrono::Utc;
 use log::{info, warn};
 use actix_web::middleware::Logger;
+use tokio::sync::broadcast;
+use jsonschema::JSONSchema;
+
+mod auth;
+mod error;
+use auth::Principal;
+use error::AppError;
+
+// Buffer depth for the sync broadcast channel. A client that falls behind by
+// more than this many committed batches is considered lagged and must
+// reconnect with `from` set to its last known `sync_id` rather than silently
+// missing writes.
+const SYNC_BROADCAST_CAPACITY: usize = 256;
+
+// How often the SSE stream sends a keep-alive comment so idle connections
+// aren't dropped by intermediate proxies/load balancers.
+const STREAM_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+// How long an interactive /api/tx session may sit idle before the TTL
+// sweep aborts it and reclaims its staged writes.
+const TX_SESSION_TTL_SECS: i64 = 300;
+
+// How often the TTL sweep runs.
+const TX_SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// An optimistic-read assertion staged alongside writes in a `/api/tx`
+/// session: the client read `field` on model `id` and expects it to still
+/// equal `expected` at commit time. If it doesn't, the whole session fails
+/// with a conflict instead of silently clobbering a concurrent write.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReadAssertion {
+    id: String,
+    field: String,
+    expected: Value,
+}
+
+/// Transactions and assertions staged for one interactive session, between
+/// `/api/tx/begin` and its eventual `/commit` or `/abort`.
+struct PendingTx {
+    /// Token of the principal that opened the session. `stage`/`commit`/
+    /// `abort` reject any caller whose token doesn't match, so sessions
+    /// keyed by a guessable sequential id can't be hijacked or interleaved
+    /// by a different token.
+    owner_token: String,
+    staged: Vec<Transaction>,
+    assertions: Vec<ReadAssertion>,
+    last_active: i64,
+}
+
+enum CommitOutcome {
+    Committed(i64),
+    Conflict { id: String, field: String },
+}
+
+/// One transaction's JSON-Schema validation errors, as reported in a 422
+/// response body when `apply_transactions` rejects a batch.
+#[derive(Debug, Serialize)]
+struct ValidationFailure {
+    id: String,
+    errors: Vec<String>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Transaction {
@@ -15,6 +78,42 @@ struct Transaction {
     id: String,
     action: String,
     data: Value,
+    /// Client-supplied HLC this write was generated at, if the client
+    /// tracks one. The server folds it into its own clock so a write that
+    /// is causally later (even from a client with a slow wall clock) still
+    /// wins the field-level merge.
+    #[serde(default)]
+    hlc: Option<Hlc>,
+    /// Stable id of the writer, used only to break ties when two writes
+    /// land on the exact same `(wall_ms, counter)`. Falls back to the
+    /// server's own node id when the client doesn't send one.
+    #[serde(default)]
+    node_id: Option<String>,
+}
+
+/// A Hybrid Logical Clock reading: `wall_ms` is a physical clock sample in
+/// milliseconds, `counter` disambiguates multiple events stamped within the
+/// same millisecond. Ordering is lexicographic on `(wall_ms, counter)`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct Hlc {
+    wall_ms: i64,
+    counter: i64,
+}
+
+/// An `Hlc` tagged with the node that produced it. Stored per-field (and
+/// once per tombstone) so `apply_transactions` can decide, field by field,
+/// whether an incoming write is newer than what's already there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FieldClock {
+    #[serde(flatten)]
+    hlc: Hlc,
+    node_id: String,
+}
+
+impl FieldClock {
+    fn wins_over(&self, other: &FieldClock) -> bool {
+        (self.hlc, &self.node_id) > (other.hlc, &other.node_id)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,6 +125,11 @@ struct TransactionResponse {
 struct TransactionsResponse {
     sync_id: i64,
     transactions: Vec<Transaction>,
+    /// Set when `from` fell at or before the compaction horizon: the log
+    /// that far back has been deleted, so the client must call
+    /// `/api/bootstrap` again instead of trusting this (empty) batch.
+    #[serde(default)]
+    needs_bootstrap: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,12 +146,17 @@ struct BootstrapResponse {
 
 struct Database {
     conn: Mutex<Connection>,
+    sync_tx: broadcast::Sender<(i64, Vec<Transaction>)>,
+    hlc: Mutex<Hlc>,
+    node_id: String,
+    pending_tx: Mutex<std::collections::BTreeMap<u32, PendingTx>>,
+    next_tx_id: AtomicU32,
 }
 
 impl Database {
-    fn new(path : &str) -> Result<Self> {
+    fn new(path: &str, node_id: String) -> Result<Self, AppError> {
         let conn = Connection::open(path)?;
-        
+
         // Create tables
         conn.execute(
             "CREATE TABLE IF NOT EXISTS sync_history (
@@ -62,72 +171,452 @@ impl Database {
                 id TEXT PRIMARY KEY,
                 model_name TEXT NOT NULL,
                 data TEXT NOT NULL,
+                field_clocks TEXT NOT NULL DEFAULT '{}',
                 created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
+                updated_at INTEGER NOT NULL,
+                deleted_at TEXT
             )",
             [],
         )?;
 
-        Ok(Database { conn: Mutex::new(conn) })
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS snapshots (
+                sync_id INTEGER PRIMARY KEY,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS auth_tokens (
+                token TEXT PRIMARY KEY,
+                allowed_models TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS model_schema (
+                model_type TEXT PRIMARY KEY,
+                schema TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        let (sync_tx, _) = broadcast::channel(SYNC_BROADCAST_CAPACITY);
+
+        Ok(Database {
+            conn: Mutex::new(conn),
+            sync_tx,
+            hlc: Mutex::new(Hlc { wall_ms: 0, counter: 0 }),
+            node_id,
+            pending_tx: Mutex::new(std::collections::BTreeMap::new()),
+            next_tx_id: AtomicU32::new(1),
+        })
+    }
+
+    /// Subscribe to newly-committed batches for the SSE stream. Each
+    /// receiver gets its own lagging cursor into the broadcast buffer.
+    fn subscribe(&self) -> broadcast::Receiver<(i64, Vec<Transaction>)> {
+        self.sync_tx.subscribe()
+    }
+
+    /// Advances the server HLC for one event and returns its new reading,
+    /// following the standard HLC receive algorithm: the new `wall_ms` is
+    /// the max of the server's own clock, its physical clock, and the
+    /// client's `wall_ms`; the new `counter` resets to `0` if `wall_ms`
+    /// advanced past both inputs, otherwise increments the max of
+    /// whichever counter(s) tied at the new `wall_ms`. This folds in a
+    /// client-supplied HLC (if any) so a write that's causally later
+    /// according to the client still wins, even if the server's own wall
+    /// clock lags behind *and* the client's counter was already ahead.
+    fn tick(&self, client_hlc: Option<Hlc>) -> Result<Hlc, AppError> {
+        let mut clock = self.hlc.lock()?;
+        let now_ms = Utc::now().timestamp_millis();
+        let client = client_hlc.unwrap_or(Hlc { wall_ms: i64::MIN, counter: 0 });
+
+        let wall_ms = clock.wall_ms.max(now_ms).max(client.wall_ms);
+
+        let counter = match (wall_ms == clock.wall_ms, wall_ms == client.wall_ms) {
+            (true, true) => clock.counter.max(client.counter) + 1,
+            (true, false) => clock.counter + 1,
+            (false, true) => client.counter + 1,
+            (false, false) => 0,
+        };
+
+        *clock = Hlc { wall_ms, counter };
+        Ok(*clock)
+    }
+
+    /// Looks up a bearer token's allowed collections. `None` means the
+    /// token is not registered at all; an empty/`["*"]` list is a scoping
+    /// decision for the caller, not this layer.
+    fn lookup_token(&self, token: &str) -> Result<Option<Vec<String>>, AppError> {
+        let conn = self.conn.lock()?;
+        let found: Option<String> = conn.query_row(
+            "SELECT allowed_models FROM auth_tokens WHERE token = ?1",
+            params![token],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+
+        Ok(match found {
+            Some(json) => Some(serde_json::from_str(&json)?),
+            None => None,
+        })
+    }
+
+    /// Registers (or replaces) a bearer token and the collections it may
+    /// touch. `allowed_models` of `["*"]` grants access to everything.
+    fn upsert_token(&self, token: &str, allowed_models: &[String]) -> Result<(), AppError> {
+        let conn = self.conn.lock()?;
+        let allowed_json = serde_json::to_string(allowed_models)?;
+        conn.execute(
+            "INSERT INTO auth_tokens (token, allowed_models) VALUES (?1, ?2)
+             ON CONFLICT(token) DO UPDATE SET allowed_models = ?2",
+            params![token, allowed_json],
+        )?;
+        Ok(())
+    }
+
+    /// Opens a new interactive transaction session, owned by `principal`,
+    /// and returns its id.
+    fn begin_tx(&self, principal: &Principal) -> Result<u32, AppError> {
+        let tx_id = self.next_tx_id.fetch_add(1, Ordering::SeqCst);
+        self.pending_tx.lock()?.insert(tx_id, PendingTx {
+            owner_token: principal.token.clone(),
+            staged: Vec::new(),
+            assertions: Vec::new(),
+            last_active: Utc::now().timestamp(),
+        });
+        Ok(tx_id)
     }
 
-    fn apply_transactions(&self, transactions: Vec<Transaction>) -> Result<i64> {
-        let mut conn = self.conn.lock().unwrap();
+    /// Buffers more writes and read-assertions onto an open session.
+    /// Fails with `AppError::NotFound` if `tx_id` is unknown (never
+    /// opened, already committed/aborted, or swept for being idle too
+    /// long), or `AppError::Forbidden` if `principal` didn't open it.
+    fn stage_tx(&self, tx_id: u32, principal: &Principal, transactions: Vec<Transaction>, assertions: Vec<ReadAssertion>) -> Result<(), AppError> {
+        let mut pending = self.pending_tx.lock()?;
+        match pending.get_mut(&tx_id) {
+            Some(session) if session.owner_token != principal.token => {
+                Err(AppError::Forbidden(format!("tx session {} belongs to a different token", tx_id)))
+            }
+            Some(session) => {
+                session.staged.extend(transactions);
+                session.assertions.extend(assertions);
+                session.last_active = Utc::now().timestamp();
+                Ok(())
+            }
+            None => Err(AppError::NotFound(format!("unknown tx session {}", tx_id))),
+        }
+    }
+
+    /// Discards a session's staged writes without applying them. Fails with
+    /// `AppError::Forbidden` if `principal` didn't open the session.
+    fn abort_tx(&self, tx_id: u32, principal: &Principal) -> Result<(), AppError> {
+        let mut pending = self.pending_tx.lock()?;
+        match pending.get(&tx_id) {
+            Some(session) if session.owner_token != principal.token => {
+                Err(AppError::Forbidden(format!("tx session {} belongs to a different token", tx_id)))
+            }
+            Some(_) => {
+                pending.remove(&tx_id);
+                Ok(())
+            }
+            None => Err(AppError::NotFound(format!("unknown tx session {}", tx_id))),
+        }
+    }
+
+    /// Checks the session's read-assertions against the current state and,
+    /// if they all still hold, applies its staged writes as a single
+    /// atomic batch (one `sync_id`). Fails with `AppError::NotFound` for
+    /// an unknown `tx_id`, or `AppError::Forbidden` if `principal` didn't
+    /// open the session. The session is only removed once its writes are
+    /// actually committed — if `apply_transactions` rejects the batch
+    /// (validation failure or storage error), the session and its staged
+    /// writes are left in place so the caller can fix the batch and retry
+    /// the same `tx_id`, or abort it explicitly, instead of losing the
+    /// session on a failed commit.
+    fn commit_tx(&self, tx_id: u32, principal: &Principal) -> Result<CommitOutcome, AppError> {
+        let (staged, assertions) = {
+            let pending = self.pending_tx.lock()?;
+            let session = pending.get(&tx_id)
+                .ok_or_else(|| AppError::NotFound(format!("unknown tx session {}", tx_id)))?;
+            if session.owner_token != principal.token {
+                return Err(AppError::Forbidden(format!("tx session {} belongs to a different token", tx_id)));
+            }
+            (session.staged.clone(), session.assertions.clone())
+        };
+
+        for assertion in &assertions {
+            let actual = self.read_field(&assertion.id, &assertion.field)?;
+            if actual.as_ref() != Some(&assertion.expected) {
+                return Ok(CommitOutcome::Conflict {
+                    id: assertion.id.clone(),
+                    field: assertion.field.clone(),
+                });
+            }
+        }
+
+        let sync_id = self.apply_transactions(staged)?;
+        self.pending_tx.lock()?.remove(&tx_id);
+        Ok(CommitOutcome::Committed(sync_id))
+    }
+
+    /// Reads a single field of the current (non-deleted) materialized
+    /// state for a model, used to check optimistic-read assertions.
+    fn read_field(&self, id: &str, field: &str) -> Result<Option<Value>, AppError> {
+        let conn = self.conn.lock()?;
+        let data_str: Option<String> = conn.query_row(
+            "SELECT data FROM model_data WHERE id = ?1 AND deleted_at IS NULL",
+            params![id],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(data_str
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+            .and_then(|v| v.get(field).cloned()))
+    }
+
+    /// Aborts any session that's been idle longer than
+    /// `TX_SESSION_TTL_SECS`, so a client that disappears mid-session
+    /// doesn't hold staged writes forever.
+    fn sweep_expired_tx_sessions(&self) -> Result<(), AppError> {
+        let now = Utc::now().timestamp();
+        self.pending_tx.lock()?
+            .retain(|_, session| now - session.last_active < TX_SESSION_TTL_SECS);
+        Ok(())
+    }
+
+    /// Looks up the JSON-Schema document registered for a collection, if
+    /// any. Collections without one stay permissive. A stored document that
+    /// fails to parse is propagated rather than treated as "no schema" —
+    /// same reasoning as the compile failure below.
+    fn get_schema(&self, model_type: &str) -> Result<Option<Value>, AppError> {
+        let conn = self.conn.lock()?;
+        let schema_str: Option<String> = conn.query_row(
+            "SELECT schema FROM model_schema WHERE model_type = ?1",
+            params![model_type],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(match schema_str {
+            Some(s) => Some(serde_json::from_str(&s)?),
+            None => None,
+        })
+    }
+
+    /// Registers (or replaces) the JSON-Schema document for a collection.
+    /// Compiles the document first so a caller finds out immediately that
+    /// `{"type":"bogus"}` doesn't compile, rather than every subsequent
+    /// create/update to the collection failing later in
+    /// `validate_transactions`.
+    fn set_schema(&self, model_type: &str, schema: &Value) -> Result<(), AppError> {
+        JSONSchema::compile(schema)
+            .map_err(|e| AppError::SchemaRejected(model_type.to_string(), e.to_string()))?;
+
+        let conn = self.conn.lock()?;
+        let schema_json = serde_json::to_string(schema)?;
+        conn.execute(
+            "INSERT INTO model_schema (model_type, schema) VALUES (?1, ?2)
+             ON CONFLICT(model_type) DO UPDATE SET schema = ?2",
+            params![model_type, schema_json],
+        )?;
+        Ok(())
+    }
+
+    /// Validates every `create`/`update` transaction's `data` against its
+    /// collection's registered schema, if one exists. Compiled schemas are
+    /// cached per batch since several transactions commonly share a
+    /// `model_type`. A failure to look up or compile a registered schema
+    /// is propagated rather than treated as "no schema" — we'd otherwise
+    /// silently let unvalidated data through on a transient DB error or a
+    /// schema that was stored but doesn't actually compile.
+    fn validate_transactions(&self, transactions: &[Transaction]) -> Result<Vec<ValidationFailure>, AppError> {
+        let mut schema_cache: std::collections::HashMap<String, Option<JSONSchema>> = std::collections::HashMap::new();
+        let mut failures = Vec::new();
+
+        for transaction in transactions {
+            if transaction.action != "create" && transaction.action != "update" {
+                continue;
+            }
+
+            if !schema_cache.contains_key(&transaction.model_type) {
+                let compiled = match self.get_schema(&transaction.model_type)? {
+                    Some(schema) => Some(JSONSchema::compile(&schema).map_err(|e| {
+                        AppError::InvalidSchema(transaction.model_type.clone(), e.to_string())
+                    })?),
+                    None => None,
+                };
+                schema_cache.insert(transaction.model_type.clone(), compiled);
+            }
+
+            if let Some(schema) = schema_cache.get(&transaction.model_type).and_then(Option::as_ref) {
+                if let Err(errors) = schema.validate(&transaction.data) {
+                    failures.push(ValidationFailure {
+                        id: transaction.id.clone(),
+                        errors: errors.map(|e| e.to_string()).collect(),
+                    });
+                }
+            }
+        }
+
+        Ok(failures)
+    }
+
+    fn apply_transactions(&self, transactions: Vec<Transaction>) -> Result<i64, AppError> {
+        let failures = self.validate_transactions(&transactions)?;
+        if !failures.is_empty() {
+            return Err(AppError::Validation(failures));
+        }
+
+        let mut conn = self.conn.lock()?;
         let tx = conn.transaction()?;
-        
+
         // Store the transactions in sync_history
-        let actions_json = serde_json::to_string(&transactions).unwrap();
+        let actions_json = serde_json::to_string(&transactions)?;
         tx.execute(
             "INSERT INTO sync_history (actions) VALUES (?1)",
             params![actions_json],
         )?;
-        
+
         let sync_id = tx.last_insert_rowid();
-        
+        let broadcast_batch = transactions.clone();
+
         // Apply each transaction to model_data
         for transaction in transactions {
             let now = Utc::now().timestamp();
-            
+
+            let node_id = transaction.node_id.clone().unwrap_or_else(|| self.node_id.clone());
+            let new_clock = FieldClock { hlc: self.tick(transaction.hlc)?, node_id };
+
             match transaction.action.as_str() {
                 "create" | "update" => {
-                    let data_json = serde_json::to_string(&transaction.data).unwrap();
+                    if !transaction.data.is_object() {
+                        return Err(AppError::InvalidData(format!(
+                            "transaction data for '{}' must be a JSON object", transaction.id
+                        )));
+                    }
+
+                    let existing: Option<(String, String, Option<String>)> = tx.query_row(
+                        "SELECT data, field_clocks, deleted_at FROM model_data WHERE id = ?1",
+                        params![transaction.id],
+                        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                    ).optional()?;
+
+                    let (mut merged_data, mut field_clocks, tombstone): (Value, std::collections::HashMap<String, FieldClock>, Option<FieldClock>) =
+                        match existing {
+                            Some((data_str, clocks_str, deleted_str)) => (
+                                serde_json::from_str(&data_str).unwrap_or_else(|_| serde_json::json!({})),
+                                serde_json::from_str(&clocks_str).unwrap_or_default(),
+                                deleted_str.and_then(|s| serde_json::from_str(&s).ok()),
+                            ),
+                            None => (serde_json::json!({}), std::collections::HashMap::new(), None),
+                        };
+
+                    // A late update/create that's causally older than the
+                    // tombstone must not resurrect the deleted model.
+                    if tombstone.as_ref().map_or(false, |t| !new_clock.wins_over(t)) {
+                        continue;
+                    }
+
+                    if let Value::Object(incoming_fields) = &transaction.data {
+                        let data_obj = merged_data.as_object_mut()
+                            .expect("model_data is always stored as a JSON object");
+                        for (field, value) in incoming_fields {
+                            let incoming_wins = field_clocks.get(field)
+                                .map_or(true, |existing_clock| new_clock.wins_over(existing_clock));
+                            if incoming_wins {
+                                data_obj.insert(field.clone(), value.clone());
+                                field_clocks.insert(field.clone(), new_clock.clone());
+                            }
+                        }
+                    }
+
+                    let data_json = serde_json::to_string(&merged_data)?;
+                    let clocks_json = serde_json::to_string(&field_clocks)?;
+
                     tx.execute(
-                        "INSERT OR REPLACE INTO model_data (id, model_name, data, created_at, updated_at)
-                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        "INSERT INTO model_data (id, model_name, data, field_clocks, created_at, updated_at, deleted_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?5, NULL)
+                         ON CONFLICT(id) DO UPDATE SET
+                            data = ?3,
+                            field_clocks = ?4,
+                            updated_at = ?5,
+                            deleted_at = NULL",
                         params![
                             transaction.id,
                             transaction.model_type,
                             data_json,
+                            clocks_json,
                             now,
-                            now
                         ],
                     )?;
                 },
                 "delete" => {
-                    tx.execute(
-                        "DELETE FROM model_data WHERE id = ?1",
+                    let existing_tombstone: Option<FieldClock> = tx.query_row(
+                        "SELECT deleted_at FROM model_data WHERE id = ?1",
                         params![transaction.id],
-                    )?;
+                        |row| row.get::<_, Option<String>>(0),
+                    ).optional()?.flatten()
+                        .and_then(|s| serde_json::from_str(&s).ok());
+
+                    let should_delete = existing_tombstone
+                        .map_or(true, |tombstone| new_clock.wins_over(&tombstone));
+
+                    if should_delete {
+                        let tombstone_json = serde_json::to_string(&new_clock)?;
+                        tx.execute(
+                            "INSERT INTO model_data (id, model_name, data, field_clocks, created_at, updated_at, deleted_at)
+                             VALUES (?1, ?2, '{}', '{}', ?3, ?3, ?4)
+                             ON CONFLICT(id) DO UPDATE SET deleted_at = ?4, updated_at = ?3",
+                            params![transaction.id, transaction.model_type, now, tombstone_json],
+                        )?;
+                    }
                 },
-                _ => return Err(rusqlite::Error::InvalidParameterName(
-                    format!("Invalid action: {}", transaction.action)
-                )),
+                _ => return Err(AppError::InvalidAction(transaction.action.clone())),
             }
         }
-        
+
         tx.commit()?;
+
+        // Publish after commit so subscribers never see a batch that could
+        // still be rolled back.
+        let _ = self.sync_tx.send((sync_id, broadcast_batch));
+
         Ok(sync_id)
     }
 
-    fn get_transactions(&self, from: i64, to: i64) -> Result<(i64, Vec<Transaction>)> {
-        let conn = self.conn.lock().unwrap();
+    /// Returns the highest `sync_id` that's been folded into a snapshot
+    /// and dropped from `sync_history`, or `0` if compaction has never
+    /// run. A `get_transactions` call starting at or before this point
+    /// can no longer be satisfied from the log alone.
+    fn compaction_horizon(&self) -> Result<i64, AppError> {
+        let conn = self.conn.lock()?;
+        Ok(conn.query_row(
+            "SELECT COALESCE(MAX(sync_id), 0) FROM snapshots",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Returns the replayed log window `[from, to]`, or signals that the
+    /// caller must re-bootstrap when `from` falls at or before the
+    /// compaction horizon, since that part of the log no longer exists.
+    fn get_transactions(&self, from: i64, to: i64) -> Result<(i64, Vec<Transaction>, bool), AppError> {
+        let horizon = self.compaction_horizon()?;
+        if horizon > 0 && from <= horizon {
+            return Ok((horizon, Vec::new(), true));
+        }
+
+        let conn = self.conn.lock()?;
         let mut stmt = conn.prepare(
             "SELECT id, actions FROM sync_history WHERE id >= ?1 AND id <= ?2"
         )?;
-        
+
         let mut transactions = Vec::new();
         let mut max_sync_id = 0;
-        
+
         let rows = stmt.query_map(params![from, to], |row| {
             let sync_id: i64 = row.get(0)?;
             let actions: String = row.get(1)?;
@@ -137,16 +626,54 @@ impl Database {
         for row in rows {
             let (sync_id, actions) = row?;
             max_sync_id = sync_id;
-            let batch: Vec<Transaction> = serde_json::from_str(&actions).unwrap();
+            let batch: Vec<Transaction> = serde_json::from_str(&actions)?;
             transactions.extend(batch);
         }
 
-        Ok((max_sync_id, transactions))
+        Ok((max_sync_id, transactions, false))
     }
 
-    fn get_bootstrap_data(&self) -> Result<(i64, std::collections::HashMap<String, Vec<ModelData>>)> {
-        let conn = self.conn.lock().unwrap();
-        
+    /// Records the latest `sync_history` id (the "horizon") in
+    /// `snapshots`, then deletes log rows at or below it. `model_data`
+    /// itself is left untouched — it's already a continuously-maintained,
+    /// field-level-merged view, so `get_bootstrap` keeps reading it
+    /// directly rather than replaying the raw log (which would have to
+    /// re-derive the HLC merge this server performs on ingest). The
+    /// `snapshots` row exists purely as a horizon marker, so
+    /// `get_transactions` can detect a `from` that the truncated log can
+    /// no longer answer — it does not duplicate `model_data`. Returns the
+    /// horizon compacted through, or `0` if there was nothing to compact.
+    fn compact(&self) -> Result<i64, AppError> {
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+
+        let horizon: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(id), 0) FROM sync_history",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if horizon == 0 {
+            return Ok(0);
+        }
+
+        let now = Utc::now().timestamp();
+        tx.execute(
+            "INSERT INTO snapshots (sync_id, created_at) VALUES (?1, ?2)
+             ON CONFLICT(sync_id) DO UPDATE SET created_at = ?2",
+            params![horizon, now],
+        )?;
+        // Only the latest snapshot is needed to explain the truncated log.
+        tx.execute("DELETE FROM snapshots WHERE sync_id < ?1", params![horizon])?;
+        tx.execute("DELETE FROM sync_history WHERE id <= ?1", params![horizon])?;
+
+        tx.commit()?;
+        Ok(horizon)
+    }
+
+    fn get_bootstrap_data(&self) -> Result<(i64, std::collections::HashMap<String, Vec<ModelData>>), AppError> {
+        let conn = self.conn.lock()?;
+
         // Get the latest sync_id
         let sync_id: i64 = conn.query_row(
             "SELECT COALESCE(MAX(id), 0) FROM sync_history",
@@ -154,11 +681,19 @@ impl Database {
             |row| row.get(0),
         )?;
 
-        // Get all model data
+        // Compaction can leave sync_history empty; the compaction horizon is
+        // then the latest sync_id folded into model_data.
+        let sync_id = sync_id.max(conn.query_row(
+            "SELECT COALESCE(MAX(sync_id), 0) FROM snapshots",
+            [],
+            |row| row.get::<_, i64>(0),
+        )?);
+
+        // Get all model data, skipping tombstoned (deleted) rows
         let mut stmt = conn.prepare(
-            "SELECT id, model_name, data FROM model_data"
+            "SELECT id, model_name, data FROM model_data WHERE deleted_at IS NULL"
         )?;
-        
+
         let rows = stmt.query_map([], |row| {
             Ok((
                 row.get::<_, String>(0)?,
@@ -168,11 +703,11 @@ impl Database {
         })?;
 
         let mut models: std::collections::HashMap<String, Vec<ModelData>> = std::collections::HashMap::new();
-        
+
         for row in rows {
             let (id, model_name, data_str) = row?;
-            let data: Value = serde_json::from_str(&data_str).unwrap();
-            
+            let data: Value = serde_json::from_str(&data_str)?;
+
             models.entry(model_name)
                 .or_insert_with(Vec::new)
                 .push(ModelData { id, data });
@@ -184,44 +719,216 @@ impl Database {
 
 async fn post_transactions(
     db: web::Data<Database>,
+    principal: web::ReqData<Principal>,
     transactions: web::Json<Vec<Transaction>>,
-) -> impl Responder {
-    match db.apply_transactions(transactions.into_inner()) {
-        Ok(sync_id) => HttpResponse::Ok().json(TransactionResponse { sync_id }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+) -> Result<HttpResponse, AppError> {
+    let transactions = transactions.into_inner();
+
+    if let Some(denied) = transactions.iter().find(|t| !principal.allows(&t.model_type)) {
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "forbidden",
+            "detail": format!("token is not authorized for model_type '{}'", denied.model_type),
+        })));
     }
+
+    let sync_id = db.apply_transactions(transactions)?;
+    Ok(HttpResponse::Ok().json(TransactionResponse { sync_id }))
+}
+
+async fn put_schema(
+    db: web::Data<Database>,
+    principal: web::ReqData<Principal>,
+    model_type: web::Path<String>,
+    schema: web::Json<Value>,
+) -> Result<HttpResponse, AppError> {
+    let model_type = model_type.into_inner();
+    if !principal.allows(&model_type) {
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "forbidden",
+            "detail": format!("token is not authorized for model_type '{}'", model_type),
+        })));
+    }
+
+    db.set_schema(&model_type, &schema.into_inner())?;
+    Ok(HttpResponse::Ok().finish())
 }
 
 async fn get_transactions(
     db: web::Data<Database>,
+    principal: web::ReqData<Principal>,
     query: web::Query<std::collections::HashMap<String, i64>>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let from = query.get("from").copied().unwrap_or(0);
     let to = query.get("to").copied().unwrap_or(i64::MAX);
-    
-    match db.get_transactions(from, to) {
-        Ok((sync_id, transactions)) => {
-            HttpResponse::Ok().json(TransactionsResponse {
-                sync_id,
-                transactions,
-            })
-        },
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+
+    let (sync_id, mut transactions, needs_bootstrap) = db.get_transactions(from, to)?;
+    transactions.retain(|t| principal.allows(&t.model_type));
+    Ok(HttpResponse::Ok().json(TransactionsResponse {
+        sync_id,
+        transactions,
+        needs_bootstrap,
+    }))
 }
 
-async fn get_bootstrap(
+/// Formats a committed batch as a single `text/event-stream` event carrying
+/// the `sync_id` and the transactions, so clients can resume with
+/// `?from=<sync_id>` if the connection drops.
+fn format_sync_event(sync_id: i64, transactions: &[Transaction]) -> serde_json::Result<String> {
+    let payload = TransactionsResponse {
+        sync_id,
+        transactions: transactions.to_vec(),
+        needs_bootstrap: false,
+    };
+    let data = serde_json::to_string(&payload)?;
+    Ok(format!("event: sync\ndata: {}\n\n", data))
+}
+
+async fn get_stream(
     db: web::Data<Database>,
+    principal: web::ReqData<Principal>,
+    query: web::Query<std::collections::HashMap<String, i64>>,
 ) -> impl Responder {
-    match db.get_bootstrap_data() {
-        Ok((sync_id, models)) => {
-            HttpResponse::Ok().json(BootstrapResponse {
-                sync_id,
-                models,
-            })
-        },
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    let from = query.get("from").copied().unwrap_or(0);
+    let mut rx = db.subscribe();
+    let principal = principal.into_inner();
+
+    let catch_up = match db.get_transactions(from, i64::MAX) {
+        Ok((sync_id, transactions, needs_bootstrap)) => Some((sync_id, transactions, needs_bootstrap)),
+        Err(e) => {
+            warn!("stream catch-up failed: {}", e);
+            None
+        }
+    };
+
+    let stream = async_stream::stream! {
+        if let Some((sync_id, transactions, needs_bootstrap)) = catch_up {
+            if needs_bootstrap {
+                yield Ok::<_, actix_web::Error>(web::Bytes::from_static(b"event: needs_bootstrap\ndata: {}\n\n"));
+            } else {
+                let visible: Vec<Transaction> = transactions.into_iter()
+                    .filter(|t| principal.allows(&t.model_type))
+                    .collect();
+                if !visible.is_empty() {
+                    if let Ok(event) = format_sync_event(sync_id, &visible) {
+                        yield Ok::<_, actix_web::Error>(web::Bytes::from(event));
+                    }
+                }
+            }
+        }
+
+        let mut keep_alive = actix_web::rt::time::interval(STREAM_KEEP_ALIVE);
+        keep_alive.tick().await; // first tick fires immediately, discard it
+
+        loop {
+            tokio::select! {
+                msg = rx.recv() => match msg {
+                    Ok((sync_id, transactions)) => {
+                        let visible: Vec<Transaction> = transactions.into_iter()
+                            .filter(|t| principal.allows(&t.model_type))
+                            .collect();
+                        if !visible.is_empty() {
+                            if let Ok(event) = format_sync_event(sync_id, &visible) {
+                                yield Ok(web::Bytes::from(event));
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        // Slow client missed buffered batches; tell it to
+                        // reconnect with a fresh `from` rather than silently
+                        // skipping writes.
+                        yield Ok(web::Bytes::from_static(b"event: lagged\ndata: {}\n\n"));
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => {
+                    yield Ok(web::Bytes::from_static(b": keep-alive\n\n"));
+                }
+            }
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .append_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+async fn get_bootstrap(
+    db: web::Data<Database>,
+    principal: web::ReqData<Principal>,
+) -> Result<HttpResponse, AppError> {
+    let (sync_id, mut models) = db.get_bootstrap_data()?;
+    models.retain(|model_type, _| principal.allows(model_type));
+    Ok(HttpResponse::Ok().json(BootstrapResponse { sync_id, models }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BeginTxResponse {
+    tx_id: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StageTxRequest {
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+    #[serde(default)]
+    assertions: Vec<ReadAssertion>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CompactResponse {
+    compacted_through: i64,
+}
+
+async fn post_compact(db: web::Data<Database>, principal: web::ReqData<Principal>) -> Result<HttpResponse, AppError> {
+    if !principal.is_admin() {
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "forbidden",
+            "detail": "compaction requires the admin ('*') capability",
+        })));
     }
+
+    let compacted_through = db.compact()?;
+    Ok(HttpResponse::Ok().json(CompactResponse { compacted_through }))
+}
+
+async fn begin_tx(db: web::Data<Database>, principal: web::ReqData<Principal>) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(BeginTxResponse { tx_id: db.begin_tx(&principal)? }))
+}
+
+async fn stage_tx(
+    db: web::Data<Database>,
+    principal: web::ReqData<Principal>,
+    path: web::Path<u32>,
+    body: web::Json<StageTxRequest>,
+) -> Result<HttpResponse, AppError> {
+    let body = body.into_inner();
+
+    if let Some(denied) = body.transactions.iter().find(|t| !principal.allows(&t.model_type)) {
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "forbidden",
+            "detail": format!("token is not authorized for model_type '{}'", denied.model_type),
+        })));
+    }
+
+    db.stage_tx(path.into_inner(), &principal, body.transactions, body.assertions)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn commit_tx(db: web::Data<Database>, principal: web::ReqData<Principal>, path: web::Path<u32>) -> Result<HttpResponse, AppError> {
+    match db.commit_tx(path.into_inner(), &principal)? {
+        CommitOutcome::Committed(sync_id) => Ok(HttpResponse::Ok().json(TransactionResponse { sync_id })),
+        CommitOutcome::Conflict { id, field } => Ok(HttpResponse::Conflict().json(serde_json::json!({
+            "error": "conflict",
+            "detail": format!("field '{}' on '{}' changed since it was read", field, id),
+        }))),
+    }
+}
+
+async fn abort_tx(db: web::Data<Database>, principal: web::ReqData<Principal>, path: web::Path<u32>) -> Result<HttpResponse, AppError> {
+    db.abort_tx(path.into_inner(), &principal)?;
+    Ok(HttpResponse::Ok().finish())
 }
 
 #[actix_web::main]
@@ -234,17 +941,52 @@ async fn main() -> std::io::Result<()> {
     info!("Starting server with database at: {}", db_path);
     info!("UI path set to: {}", ui_path);
     
-    let db = web::Data::new(Database::new(&db_path).unwrap());
-    
+    let node_id = std::env::var("NODE_ID").unwrap_or_else(|_| uuid::Uuid::new_v4().to_string());
+    let db = web::Data::new(Database::new(&db_path, node_id).unwrap());
+
+    // Bootstrap tokens from the environment, e.g.
+    // AUTH_TOKENS="abc123:Todo|Note,admin-token:*"
+    if let Ok(raw) = std::env::var("AUTH_TOKENS") {
+        for entry in raw.split(',').filter(|e| !e.is_empty()) {
+            if let Some((token, models)) = entry.split_once(':') {
+                let allowed_models: Vec<String> = models.split('|').map(String::from).collect();
+                if let Err(e) = db.upsert_token(token, &allowed_models) {
+                    warn!("failed to register token from AUTH_TOKENS: {}", e);
+                }
+            }
+        }
+    }
+
+    {
+        let db = db.clone();
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(TX_SESSION_SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = db.sweep_expired_tx_sessions() {
+                    warn!("tx session sweep failed: {}", e);
+                }
+            }
+        });
+    }
+
     let server = HttpServer::new(move || {
         App::new()
             .app_data(db.clone())
             .wrap(Logger::new("%a '%r' %s %b '%{Referer}i' '%{User-Agent}i' %T"))
             .service(
                 web::scope("/api")
+                    .wrap(auth::RequireAuth)
                     .route("/transactions", web::post().to(post_transactions))
                     .route("/transactions", web::get().to(get_transactions))
+                    .route("/stream", web::get().to(get_stream))
                     .route("/bootstrap", web::get().to(get_bootstrap))
+                    .route("/compact", web::post().to(post_compact))
+                    .route("/schema/{model_type}", web::put().to(put_schema))
+                    .route("/tx/begin", web::post().to(begin_tx))
+                    .route("/tx/{id}/commit", web::post().to(commit_tx))
+                    .route("/tx/{id}/abort", web::post().to(abort_tx))
+                    .route("/tx/{id}", web::post().to(stage_tx))
             )
             .service(Files::new("/", &ui_path).index_file("index.html"))
     })
@@ -261,22 +1003,32 @@ mod tests {
     use tempfile::tempdir;
     use uuid::Uuid;
 
+    const TEST_TOKEN: &str = "test-token";
+
     async fn setup_test_app() -> (web::Data<Database>, String) {
         let temp_dir = tempdir().unwrap();
         let db_path = temp_dir.path().join("test.db");
         let db_path_str = db_path.to_str().unwrap().to_string();
-        
-        let db = web::Data::new(Database::new(&db_path_str).expect("Failed to create test database"));
+
+        let db = web::Data::new(
+            Database::new(&db_path_str, "test-node".to_string()).expect("Failed to create test database")
+        );
+        db.upsert_token(TEST_TOKEN, &["*".to_string()]).unwrap();
         (db, db_path_str)
     }
 
+    fn auth_header() -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", TEST_TOKEN))
+    }
+
     #[actix_rt::test]
     async fn test_post_transactions() {
         let (db, _) = setup_test_app().await;
-        
+
         let app = test::init_service(
             App::new()
                 .app_data(db.clone())
+                .wrap(auth::RequireAuth)
                 .route("/api/transactions", web::post().to(post_transactions))
         ).await;
 
@@ -288,10 +1040,13 @@ mod tests {
                 "title": "Test todo",
                 "completed": false
             }),
+            hlc: None,
+            node_id: None,
         };
 
         let req = test::TestRequest::post()
             .uri("/api/transactions")
+            .insert_header(auth_header())
             .set_json(vec![transaction])
             .to_request();
 
@@ -302,10 +1057,11 @@ mod tests {
     #[actix_rt::test]
     async fn test_get_transactions() {
         let (db, _) = setup_test_app().await;
-        
+
         let app = test::init_service(
             App::new()
                 .app_data(db.clone())
+                .wrap(auth::RequireAuth)
                 .route("/api/transactions", web::post().to(post_transactions))
                 .route("/api/transactions", web::get().to(get_transactions))
         ).await;
@@ -319,22 +1075,26 @@ mod tests {
                 "title": "Test todo",
                 "completed": false
             }),
+            hlc: None,
+            node_id: None,
         };
 
         let create_req = test::TestRequest::post()
             .uri("/api/transactions")
+            .insert_header(auth_header())
             .set_json(vec![transaction.clone()])
             .to_request();
 
         let create_resp: TransactionResponse = test::call_and_read_body_json(&app, create_req).await;
-        
+
         // Then fetch transactions
         let get_req = test::TestRequest::get()
             .uri(&format!("/api/transactions?from=0&to={}", create_resp.sync_id))
+            .insert_header(auth_header())
             .to_request();
 
         let get_resp: TransactionsResponse = test::call_and_read_body_json(&app, get_req).await;
-        
+
         assert_eq!(get_resp.transactions.len(), 1);
         assert_eq!(get_resp.transactions[0].id, transaction.id);
     }
@@ -342,10 +1102,11 @@ mod tests {
     #[actix_rt::test]
     async fn test_bootstrap() {
         let (db, _) = setup_test_app().await;
-        
+
         let app = test::init_service(
             App::new()
                 .app_data(db.clone())
+                .wrap(auth::RequireAuth)
                 .route("/api/transactions", web::post().to(post_transactions))
                 .route("/api/bootstrap", web::get().to(get_bootstrap))
         ).await;
@@ -360,6 +1121,8 @@ mod tests {
                     "title": "Todo 1",
                     "completed": false
                 }),
+                hlc: None,
+                node_id: None,
             },
             Transaction {
                 model_type: "Todo".to_string(),
@@ -369,23 +1132,27 @@ mod tests {
                     "title": "Todo 2",
                     "completed": true
                 }),
+                hlc: None,
+                node_id: None,
             },
         ];
 
         let create_req = test::TestRequest::post()
             .uri("/api/transactions")
+            .insert_header(auth_header())
             .set_json(transactions)
             .to_request();
 
         let _: TransactionResponse = test::call_and_read_body_json(&app, create_req).await;
-        
+
         // Get bootstrap data
         let bootstrap_req = test::TestRequest::get()
             .uri("/api/bootstrap")
+            .insert_header(auth_header())
             .to_request();
 
         let bootstrap_resp: BootstrapResponse = test::call_and_read_body_json(&app, bootstrap_req).await;
-        
+
         assert!(bootstrap_resp.sync_id > 0);
         assert!(bootstrap_resp.models.contains_key("Todo"));
         assert_eq!(bootstrap_resp.models["Todo"].len(), 2);
@@ -394,16 +1161,17 @@ mod tests {
     #[actix_rt::test]
     async fn test_transaction_crud_operations() {
         let (db, _) = setup_test_app().await;
-        
+
         let app = test::init_service(
             App::new()
                 .app_data(db.clone())
+                .wrap(auth::RequireAuth)
                 .route("/api/transactions", web::post().to(post_transactions))
                 .route("/api/bootstrap", web::get().to(get_bootstrap))
         ).await;
 
         let todo_id = Uuid::new_v4().to_string();
-        
+
         // Test Create
         let create_transaction = Transaction {
             model_type: "Todo".to_string(),
@@ -413,10 +1181,13 @@ mod tests {
                 "title": "Original todo",
                 "completed": false
             }),
+            hlc: None,
+            node_id: None,
         };
 
         let create_req = test::TestRequest::post()
             .uri("/api/transactions")
+            .insert_header(auth_header())
             .set_json(vec![create_transaction])
             .to_request();
 
@@ -431,10 +1202,13 @@ mod tests {
                 "title": "Updated todo",
                 "completed": true
             }),
+            hlc: None,
+            node_id: None,
         };
 
         let update_req = test::TestRequest::post()
             .uri("/api/transactions")
+            .insert_header(auth_header())
             .set_json(vec![update_transaction])
             .to_request();
 
@@ -446,10 +1220,13 @@ mod tests {
             id: todo_id.clone(),
             action: "delete".to_string(),
             data: serde_json::json!({}),
+            hlc: None,
+            node_id: None,
         };
 
         let delete_req = test::TestRequest::post()
             .uri("/api/transactions")
+            .insert_header(auth_header())
             .set_json(vec![delete_transaction])
             .to_request();
 
@@ -458,13 +1235,265 @@ mod tests {
         // Verify final state
         let bootstrap_req = test::TestRequest::get()
             .uri("/api/bootstrap")
+            .insert_header(auth_header())
             .to_request();
 
         let bootstrap_resp: BootstrapResponse = test::call_and_read_body_json(&app, bootstrap_req).await;
-        
+
         // Todo should be deleted
-        assert!(bootstrap_resp.models.get("Todo").map_or(true, |todos| 
+        assert!(bootstrap_resp.models.get("Todo").map_or(true, |todos|
             !todos.iter().any(|t| t.id == todo_id)
         ));
     }
+
+    #[actix_rt::test]
+    async fn test_unauthenticated_request_rejected() {
+        let (db, _) = setup_test_app().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .wrap(auth::RequireAuth)
+                .route("/api/bootstrap", web::get().to(get_bootstrap))
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/bootstrap").to_request();
+        // The middleware rejects with an `Err` that the server turns into a
+        // 401 response; `test::call_service` would unwrap it, so call the
+        // service directly.
+        use actix_web::dev::Service as _;
+        let err = app.call(req).await.err().expect("request without a bearer token should be rejected");
+
+        assert_eq!(err.as_response_error().status_code(), actix_web::http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_scoped_token_rejected_for_other_collection() {
+        let (db, _) = setup_test_app().await;
+        db.upsert_token("todo-only", &["Todo".to_string()]).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .wrap(auth::RequireAuth)
+                .route("/api/transactions", web::post().to(post_transactions))
+        ).await;
+
+        let transaction = Transaction {
+            model_type: "Note".to_string(),
+            id: Uuid::new_v4().to_string(),
+            action: "create".to_string(),
+            data: serde_json::json!({ "body": "not allowed" }),
+            hlc: None,
+            node_id: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/api/transactions")
+            .insert_header(("Authorization", "Bearer todo-only"))
+            .set_json(vec![transaction])
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_tx_session_commit() {
+        let (db, _) = setup_test_app().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .wrap(auth::RequireAuth)
+                .route("/api/tx/begin", web::post().to(begin_tx))
+                .route("/api/tx/{id}/commit", web::post().to(commit_tx))
+                .route("/api/tx/{id}", web::post().to(stage_tx))
+                .route("/api/bootstrap", web::get().to(get_bootstrap))
+        ).await;
+
+        let begin_req = test::TestRequest::post()
+            .uri("/api/tx/begin")
+            .insert_header(auth_header())
+            .to_request();
+        let begin_resp: BeginTxResponse = test::call_and_read_body_json(&app, begin_req).await;
+
+        let stage_req = test::TestRequest::post()
+            .uri(&format!("/api/tx/{}", begin_resp.tx_id))
+            .insert_header(auth_header())
+            .set_json(serde_json::json!({
+                "transactions": [{
+                    "type": "Todo",
+                    "id": Uuid::new_v4().to_string(),
+                    "action": "create",
+                    "data": { "title": "Staged todo", "completed": false }
+                }]
+            }))
+            .to_request();
+        let stage_resp = test::call_service(&app, stage_req).await;
+        assert_eq!(stage_resp.status(), actix_web::http::StatusCode::OK);
+
+        let commit_req = test::TestRequest::post()
+            .uri(&format!("/api/tx/{}/commit", begin_resp.tx_id))
+            .insert_header(auth_header())
+            .to_request();
+        let commit_resp: TransactionResponse = test::call_and_read_body_json(&app, commit_req).await;
+        assert!(commit_resp.sync_id > 0);
+
+        let bootstrap_req = test::TestRequest::get()
+            .uri("/api/bootstrap")
+            .insert_header(auth_header())
+            .to_request();
+        let bootstrap_resp: BootstrapResponse = test::call_and_read_body_json(&app, bootstrap_req).await;
+        assert_eq!(bootstrap_resp.models["Todo"].len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_tx_session_abort_discards_staged_writes() {
+        let (db, _) = setup_test_app().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .wrap(auth::RequireAuth)
+                .route("/api/tx/begin", web::post().to(begin_tx))
+                .route("/api/tx/{id}/abort", web::post().to(abort_tx))
+                .route("/api/tx/{id}/commit", web::post().to(commit_tx))
+                .route("/api/tx/{id}", web::post().to(stage_tx))
+        ).await;
+
+        let begin_req = test::TestRequest::post()
+            .uri("/api/tx/begin")
+            .insert_header(auth_header())
+            .to_request();
+        let begin_resp: BeginTxResponse = test::call_and_read_body_json(&app, begin_req).await;
+
+        let stage_req = test::TestRequest::post()
+            .uri(&format!("/api/tx/{}", begin_resp.tx_id))
+            .insert_header(auth_header())
+            .set_json(serde_json::json!({
+                "transactions": [{
+                    "type": "Todo",
+                    "id": Uuid::new_v4().to_string(),
+                    "action": "create",
+                    "data": { "title": "Should not land", "completed": false }
+                }]
+            }))
+            .to_request();
+        test::call_service(&app, stage_req).await;
+
+        let abort_req = test::TestRequest::post()
+            .uri(&format!("/api/tx/{}/abort", begin_resp.tx_id))
+            .insert_header(auth_header())
+            .to_request();
+        let abort_resp = test::call_service(&app, abort_req).await;
+        assert_eq!(abort_resp.status(), actix_web::http::StatusCode::OK);
+
+        // The session is gone, so committing it afterwards is a 404.
+        let commit_req = test::TestRequest::post()
+            .uri(&format!("/api/tx/{}/commit", begin_resp.tx_id))
+            .insert_header(auth_header())
+            .to_request();
+        let commit_resp = test::call_service(&app, commit_req).await;
+        assert_eq!(commit_resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_compact_signals_needs_bootstrap_for_truncated_range() {
+        let (db, _) = setup_test_app().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .wrap(auth::RequireAuth)
+                .route("/api/transactions", web::post().to(post_transactions))
+                .route("/api/transactions", web::get().to(get_transactions))
+                .route("/api/compact", web::post().to(post_compact))
+                .route("/api/bootstrap", web::get().to(get_bootstrap))
+        ).await;
+
+        let transaction = Transaction {
+            model_type: "Todo".to_string(),
+            id: Uuid::new_v4().to_string(),
+            action: "create".to_string(),
+            data: serde_json::json!({ "title": "Pre-compaction todo", "completed": false }),
+            hlc: None,
+            node_id: None,
+        };
+
+        let create_req = test::TestRequest::post()
+            .uri("/api/transactions")
+            .insert_header(auth_header())
+            .set_json(vec![transaction])
+            .to_request();
+        let _: TransactionResponse = test::call_and_read_body_json(&app, create_req).await;
+
+        let compact_req = test::TestRequest::post()
+            .uri("/api/compact")
+            .insert_header(auth_header())
+            .to_request();
+        let compact_resp: CompactResponse = test::call_and_read_body_json(&app, compact_req).await;
+        assert!(compact_resp.compacted_through > 0);
+
+        // The compacted range is gone from the log; the client must
+        // re-bootstrap instead of getting a (wrongly) empty batch.
+        let get_req = test::TestRequest::get()
+            .uri("/api/transactions?from=0")
+            .insert_header(auth_header())
+            .to_request();
+        let get_resp: TransactionsResponse = test::call_and_read_body_json(&app, get_req).await;
+        assert!(get_resp.needs_bootstrap);
+
+        // The materialized state (and therefore bootstrap) is unaffected
+        // by compaction.
+        let bootstrap_req = test::TestRequest::get()
+            .uri("/api/bootstrap")
+            .insert_header(auth_header())
+            .to_request();
+        let bootstrap_resp: BootstrapResponse = test::call_and_read_body_json(&app, bootstrap_req).await;
+        assert_eq!(bootstrap_resp.models["Todo"].len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_schema_validation_rejects_invalid_data() {
+        let (db, _) = setup_test_app().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .wrap(auth::RequireAuth)
+                .route("/api/schema/{model_type}", web::put().to(put_schema))
+                .route("/api/transactions", web::post().to(post_transactions))
+        ).await;
+
+        let schema_req = test::TestRequest::put()
+            .uri("/api/schema/Todo")
+            .insert_header(auth_header())
+            .set_json(serde_json::json!({
+                "type": "object",
+                "required": ["title"],
+                "properties": { "title": { "type": "string" } }
+            }))
+            .to_request();
+        let schema_resp = test::call_service(&app, schema_req).await;
+        assert_eq!(schema_resp.status(), actix_web::http::StatusCode::OK);
+
+        let invalid_transaction = Transaction {
+            model_type: "Todo".to_string(),
+            id: Uuid::new_v4().to_string(),
+            action: "create".to_string(),
+            data: serde_json::json!({ "completed": false }),
+            hlc: None,
+            node_id: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/api/transactions")
+            .insert_header(auth_header())
+            .set_json(vec![invalid_transaction])
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
 }